@@ -0,0 +1,125 @@
+use crate::config::ClientConfig;
+use crate::utils::{send_request, OpenAIDecodingArguments};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// A pluggable backend that can turn a batch of prompts into completions.
+///
+/// This abstracts over where/how the actual HTTP request is made so that
+/// [`crate::utils::openai_completion`]'s batching, retry, and `return_text`/`n`-chunking
+/// logic can run unchanged against OpenAI, an Azure/proxy gateway, or a
+/// local/self-hosted OpenAI-compatible server (llama.cpp server, TGI, vLLM, ...).
+#[async_trait]
+pub trait CompletionBackend: Send + Sync {
+    async fn complete(
+        &self,
+        prompts: &[Value],
+        args: &OpenAIDecodingArguments,
+    ) -> Result<Vec<Value>, Box<dyn Error>>;
+}
+
+/// Talks to the official OpenAI completions API, or any OpenAI-compatible gateway
+/// sharing its request shape (e.g. Azure OpenAI, a proxy) via `config.base_url`.
+pub struct OpenAiBackend {
+    pub config: ClientConfig,
+    pub model_name: String,
+    pub decoding_kwargs: HashMap<String, Value>,
+}
+
+impl OpenAiBackend {
+    /// Creates a backend reading its `ClientConfig` from the environment
+    /// (`OPENAI_API_KEY`, `OPENAI_BASE_URL`, `OPENAI_ORGANIZATION`).
+    pub fn new(model_name: impl Into<String>) -> Self {
+        OpenAiBackend {
+            config: ClientConfig::from_env(),
+            model_name: model_name.into(),
+            decoding_kwargs: HashMap::new(),
+        }
+    }
+
+    /// Creates a backend with an explicit `ClientConfig`, e.g. for Azure OpenAI or a proxy.
+    pub fn with_config(config: ClientConfig, model_name: impl Into<String>) -> Self {
+        OpenAiBackend {
+            config,
+            model_name: model_name.into(),
+            decoding_kwargs: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionBackend for OpenAiBackend {
+    async fn complete(
+        &self,
+        prompts: &[Value],
+        args: &OpenAIDecodingArguments,
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let client = Client::new();
+        let url = format!(
+            "{}/v1/engines/{}/completions",
+            self.config.base_url.trim_end_matches('/'),
+            self.model_name
+        );
+        send_request(
+            &client,
+            &url,
+            &self.config,
+            &self.model_name,
+            prompts,
+            args,
+            &self.decoding_kwargs,
+        )
+        .await
+    }
+}
+
+/// Talks to a local/self-hosted OpenAI-compatible completions server, e.g. a
+/// llama.cpp server, text-generation-inference, or vLLM instance.
+///
+/// These servers typically don't check an API key, so `config.api_key` defaults
+/// to an empty bearer token.
+pub struct LocalBackend {
+    pub config: ClientConfig,
+    pub model_name: String,
+    pub decoding_kwargs: HashMap<String, Value>,
+}
+
+impl LocalBackend {
+    /// Creates a backend targeting `base_url` with no API key and no extra decoding kwargs.
+    pub fn new(base_url: impl Into<String>, model_name: impl Into<String>) -> Self {
+        LocalBackend {
+            config: ClientConfig {
+                base_url: base_url.into(),
+                api_key: String::new(),
+                organization: None,
+            },
+            model_name: model_name.into(),
+            decoding_kwargs: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionBackend for LocalBackend {
+    async fn complete(
+        &self,
+        prompts: &[Value],
+        args: &OpenAIDecodingArguments,
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let client = Client::new();
+        let url = format!("{}/v1/completions", self.config.base_url.trim_end_matches('/'));
+        send_request(
+            &client,
+            &url,
+            &self.config,
+            &self.model_name,
+            prompts,
+            args,
+            &self.decoding_kwargs,
+        )
+        .await
+    }
+}
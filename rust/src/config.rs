@@ -0,0 +1,34 @@
+use std::env;
+
+/// Connection details for an OpenAI-compatible HTTP API.
+///
+/// Defaults are read from the environment (`OPENAI_API_KEY`, `OPENAI_BASE_URL`,
+/// `OPENAI_ORGANIZATION`), but every field can be overridden explicitly. This is
+/// what lets the crate talk to Azure OpenAI, a proxy, or any other
+/// OpenAI-compatible gateway instead of only `api.openai.com`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    pub base_url: String,
+    pub api_key: String,
+    pub organization: Option<String>,
+}
+
+impl ClientConfig {
+    /// Builds a `ClientConfig` from the environment: `OPENAI_API_KEY` (empty if
+    /// unset), `OPENAI_BASE_URL` (defaulting to `https://api.openai.com`), and
+    /// `OPENAI_ORGANIZATION` (absent if unset).
+    pub fn from_env() -> Self {
+        ClientConfig {
+            base_url: env::var("OPENAI_BASE_URL")
+                .unwrap_or_else(|_| "https://api.openai.com".to_string()),
+            api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
+            organization: env::var("OPENAI_ORGANIZATION").ok(),
+        }
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
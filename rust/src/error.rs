@@ -0,0 +1,41 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::Duration;
+
+/// A classified failure from an OpenAI-compatible completions API.
+///
+/// Callers can match on this instead of string-matching the error message, and
+/// the retry loop in [`crate::utils::openai_completion`] uses it to decide
+/// whether (and how long) to back off.
+#[derive(Debug)]
+pub enum OpenAiError {
+    /// HTTP 429 or 503. Carries the `Retry-After` duration when the server sent it as
+    /// a whole number of seconds; an HTTP-date `Retry-After` value is not parsed.
+    RateLimited { retry_after: Option<Duration> },
+    /// The prompt (plus requested completion) exceeds the model's context window.
+    ContextLengthExceeded,
+    /// HTTP 401/403: invalid or missing API key.
+    Auth,
+    /// Any other non-2xx response.
+    Other(String),
+}
+
+impl fmt::Display for OpenAiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenAiError::RateLimited {
+                retry_after: Some(d),
+            } => write!(f, "OpenAIError: rate limited, retry after {:?}", d),
+            OpenAiError::RateLimited { retry_after: None } => {
+                write!(f, "OpenAIError: rate limited")
+            }
+            OpenAiError::ContextLengthExceeded => {
+                write!(f, "OpenAIError: context length exceeded")
+            }
+            OpenAiError::Auth => write!(f, "OpenAIError: authentication failed"),
+            OpenAiError::Other(message) => write!(f, "OpenAIError: {}", message),
+        }
+    }
+}
+
+impl StdError for OpenAiError {}
@@ -1,6 +1,10 @@
+use crate::rouge::{rouge_l_similarity, tokenize};
+use crate::utils::{jdump, jload};
+use rayon::prelude::*;
 use regex::Regex;
 use std::fs::File;
 use std::io::{prelude::*, Error};
+use std::path::Path;
 
 pub fn encode_prompt(prompt_instructions: &Vec<(&str, &str, &str)>) -> Result<String, Error> {
     let mut prompt = String::new();
@@ -37,3 +41,61 @@ pub fn encode_prompt(prompt_instructions: &Vec<(&str, &str, &str)>) -> Result<St
 
     Ok(prompt)
 }
+
+/// Filters newly generated instructions against the seed + already-accepted pool,
+/// the core quality step of the Alpaca/self-instruct pipeline: an instruction is
+/// dropped if its ROUGE-L similarity against anything already in `pool` exceeds
+/// `threshold`. Accepted instructions are appended to `pool` as they're kept, so
+/// later candidates in the same batch are also deduplicated against earlier ones.
+///
+/// The candidate-vs-pool scan is parallelized with rayon to keep the O(n·m)
+/// comparison tractable as the pool grows across a long generation run.
+pub fn filter_similar_instructions(
+    candidates: Vec<String>,
+    pool: &mut Vec<String>,
+    threshold: f32,
+) -> Vec<String> {
+    let mut kept = Vec::new();
+
+    for candidate in candidates {
+        let candidate_tokens = tokenize(&candidate);
+        let candidate_tokens: Vec<&str> = candidate_tokens.iter().map(String::as_str).collect();
+
+        let max_similarity = pool
+            .par_iter()
+            .map(|existing| {
+                let existing_tokens = tokenize(existing);
+                let existing_tokens: Vec<&str> =
+                    existing_tokens.iter().map(String::as_str).collect();
+                rouge_l_similarity(&candidate_tokens, &existing_tokens)
+            })
+            .reduce(|| 0.0_f32, f32::max);
+
+        if max_similarity <= threshold {
+            pool.push(candidate.clone());
+            kept.push(candidate);
+        }
+    }
+
+    kept
+}
+
+/// Merges `instructions` into the JSON array already stored at `path` and rewrites
+/// it via [`jdump`], so kept instructions accumulate across runs of the generation
+/// loop. `jdump`'s "append" mode would just concatenate raw JSON documents, which
+/// `jload` can't parse back, so the existing pool is read first and the union is
+/// written out as a single valid JSON array.
+pub fn save_instructions<P: AsRef<Path>>(
+    instructions: &[String],
+    path: P,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let mut merged: Vec<String> = if path.exists() {
+        jload(path)?
+    } else {
+        Vec::new()
+    };
+    merged.extend(instructions.iter().cloned());
+
+    jdump(&merged, path, "write", None)
+}
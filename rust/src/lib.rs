@@ -0,0 +1,8 @@
+pub mod backend;
+pub mod config;
+pub mod error;
+pub mod generate_instruction;
+pub mod rouge;
+pub mod tools;
+pub mod utils;
+pub mod vision;
@@ -0,0 +1,87 @@
+/// Default maximum ROUGE-L similarity a new instruction may have against the
+/// seed + already-accepted pool before it's rejected as a near-duplicate.
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.7;
+
+/// Splits `text` into lowercased tokens on whitespace and punctuation.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Length of the longest common subsequence of `a` and `b`, via the standard
+/// O(len(a)·len(b)) DP table.
+fn longest_common_subsequence(a: &[&str], b: &[&str]) -> usize {
+    let mut dp = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            dp[i][j] = if a[i - 1] == b[j - 1] {
+                dp[i - 1][j - 1] + 1
+            } else {
+                dp[i - 1][j].max(dp[i][j - 1])
+            };
+        }
+    }
+    dp[a.len()][b.len()]
+}
+
+/// Computes the ROUGE-L F-measure (β=1) between two already-tokenized sequences,
+/// based on the length of their longest common subsequence.
+///
+/// Returns 0.0 if either sequence is empty.
+pub fn rouge_l_similarity(candidate: &[&str], reference: &[&str]) -> f32 {
+    if candidate.is_empty() || reference.is_empty() {
+        return 0.0;
+    }
+
+    let lcs_len = longest_common_subsequence(candidate, reference) as f32;
+    let recall = lcs_len / reference.len() as f32;
+    let precision = lcs_len / candidate.len() as f32;
+
+    if recall + precision == 0.0 {
+        return 0.0;
+    }
+
+    2.0 * precision * recall / (recall + precision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_sequences_score_one() {
+        let tokens: Vec<&str> = vec!["write", "a", "poem", "about", "the", "sea"];
+        assert_eq!(rouge_l_similarity(&tokens, &tokens), 1.0);
+    }
+
+    #[test]
+    fn mostly_disjoint_sequences_score_low() {
+        let a = vec!["write", "a", "poem"];
+        let b = vec!["bake", "a", "cake"];
+        // LCS is just "a", so recall and precision are both 1/3 -> F-measure 1/3.
+        assert!((rouge_l_similarity(&a, &b) - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn empty_sequence_scores_zero() {
+        let tokens = vec!["non", "empty"];
+        let empty: Vec<&str> = vec![];
+        assert_eq!(rouge_l_similarity(&tokens, &empty), 0.0);
+        assert_eq!(rouge_l_similarity(&empty, &tokens), 0.0);
+    }
+
+    #[test]
+    fn asymmetric_lengths_weigh_precision_and_recall_separately() {
+        // candidate is a subsequence of reference, so precision is perfect (every
+        // candidate token is matched) while recall is penalized by the extra token.
+        let candidate = vec!["a", "b"];
+        let reference = vec!["a", "x", "b"];
+        let expected_recall = 2.0 / 3.0;
+        let expected_precision = 1.0;
+        let expected_f =
+            2.0 * expected_precision * expected_recall / (expected_precision + expected_recall);
+        assert!((rouge_l_similarity(&candidate, &reference) - expected_f).abs() < 1e-6);
+    }
+}
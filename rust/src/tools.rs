@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A function the model may call, described in OpenAI's function-calling schema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+impl ToolSpec {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+    ) -> Self {
+        ToolSpec {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        }
+    }
+
+    /// Serializes this spec into the `{"type": "function", "function": {...}}` shape
+    /// the Chat Completions `tools` array expects.
+    pub(crate) fn to_request_value(&self) -> Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            },
+        })
+    }
+}
+
+/// Controls whether/which tool the model should call, mirroring OpenAI's `tool_choice`.
+#[derive(Debug, Clone)]
+pub enum ToolChoice {
+    Auto,
+    None,
+    Required,
+    Function(String),
+}
+
+impl ToolChoice {
+    pub(crate) fn to_request_value(&self) -> Value {
+        match self {
+            ToolChoice::Auto => Value::String("auto".to_string()),
+            ToolChoice::None => Value::String("none".to_string()),
+            ToolChoice::Required => Value::String("required".to_string()),
+            ToolChoice::Function(name) => serde_json::json!({
+                "type": "function",
+                "function": { "name": name },
+            }),
+        }
+    }
+}
+
+/// A single tool call the model requested, with its arguments already parsed out
+/// of OpenAI's JSON-encoded-string wire format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Extracts the `tool_calls` requested by a Chat Completions `message` object, if any.
+///
+/// `message` is the raw `choices[i].message` value. Calls whose `function.arguments`
+/// isn't valid JSON are skipped rather than failing the whole batch.
+pub fn parse_tool_calls(message: &Value) -> Vec<ToolCall> {
+    let Some(calls) = message["tool_calls"].as_array() else {
+        return Vec::new();
+    };
+
+    calls
+        .iter()
+        .filter_map(|call| {
+            let id = call["id"].as_str()?.to_string();
+            let name = call["function"]["name"].as_str()?.to_string();
+            let arguments = call["function"]["arguments"]
+                .as_str()
+                .and_then(|raw| serde_json::from_str(raw).ok())?;
+            Some(ToolCall {
+                id,
+                name,
+                arguments,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_tool_calls_returns_empty() {
+        let message = serde_json::json!({"role": "assistant", "content": "hi"});
+        assert!(parse_tool_calls(&message).is_empty());
+    }
+
+    #[test]
+    fn parses_id_name_and_json_arguments() {
+        let message = serde_json::json!({
+            "role": "assistant",
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "arguments": "{\"city\": \"Paris\"}",
+                },
+            }],
+        });
+        let calls = parse_tool_calls(&message);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].name, "get_weather");
+        assert_eq!(calls[0].arguments, serde_json::json!({"city": "Paris"}));
+    }
+
+    #[test]
+    fn skips_call_with_invalid_json_arguments() {
+        let message = serde_json::json!({
+            "tool_calls": [
+                {
+                    "id": "call_1",
+                    "function": {"name": "broken", "arguments": "not json"},
+                },
+                {
+                    "id": "call_2",
+                    "function": {"name": "ok", "arguments": "{}"},
+                },
+            ],
+        });
+        let calls = parse_tool_calls(&message);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_2");
+    }
+}
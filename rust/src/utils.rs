@@ -1,4 +1,12 @@
-use reqwest::{Client, StatusCode};
+use crate::backend::CompletionBackend;
+use crate::config::ClientConfig;
+use crate::error::OpenAiError;
+use crate::tools::{ToolCall, ToolChoice, ToolSpec};
+use crate::vision::{is_vision_model, ContentPart, MessageContent, VISION_DEFAULT_MAX_TOKENS};
+use futures::stream::{self, unfold, Stream, StreamExt};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use std::collections::HashMap;
@@ -7,9 +15,110 @@ use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter};
 use std::option::Option;
 use std::path::Path;
-use std::thread;
 use std::time::Duration;
 use std::vec::Vec;
+use tokio::sync::mpsc;
+
+/// One prompt batch's position in the original ordering paired with its completion
+/// result, used to reassemble `openai_completion`'s concurrent batch dispatch in order.
+type BatchResult = (usize, Result<Vec<Value>, Box<dyn Error>>);
+
+/// A single message in a Chat Completions conversation.
+///
+/// `role` is one of `"system"`, `"user"`, `"assistant"`, or `"tool"`. `tool_call_id`
+/// and `tool_calls` only apply to the function-calling flow: a `"tool"` message
+/// carries the `tool_call_id` it's answering, and an `"assistant"` message that
+/// requested tool calls carries them back in `tool_calls` so the follow-up request
+/// can correlate the `"tool"` replies with it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: MessageContent,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tool_call_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tool_calls: Option<Vec<Value>>,
+}
+
+impl ChatMessage {
+    /// Convenience constructor for a `system` message.
+    pub fn system<S: Into<String>>(content: S) -> Self {
+        ChatMessage {
+            role: "system".to_string(),
+            content: MessageContent::Text(content.into()),
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    /// Convenience constructor for a `user` message.
+    pub fn user<S: Into<String>>(content: S) -> Self {
+        ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text(content.into()),
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    /// Convenience constructor for a `user` message made of multiple parts, so a
+    /// vision-capable model can be sent text alongside one or more images via
+    /// [`ContentPart::image`].
+    pub fn user_multimodal(parts: Vec<ContentPart>) -> Self {
+        ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Parts(parts),
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    /// Convenience constructor for an `assistant` message.
+    pub fn assistant<S: Into<String>>(content: S) -> Self {
+        ChatMessage {
+            role: "assistant".to_string(),
+            content: MessageContent::Text(content.into()),
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    /// Convenience constructor for a `tool` message reporting the result of running
+    /// `tool_call_id` back to the model.
+    pub fn tool<S: Into<String>>(tool_call_id: impl Into<String>, content: S) -> Self {
+        ChatMessage {
+            role: "tool".to_string(),
+            content: MessageContent::Text(content.into()),
+            tool_call_id: Some(tool_call_id.into()),
+            tool_calls: None,
+        }
+    }
+
+    /// Convenience constructor for the `assistant` message that re-issues the model's
+    /// own tool calls, so a subsequent `tool` message can be correlated with them.
+    pub fn assistant_tool_calls(tool_calls: &[ToolCall]) -> Self {
+        let tool_calls = tool_calls
+            .iter()
+            .map(|call| {
+                serde_json::json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.name,
+                        "arguments": serde_json::to_string(&call.arguments).unwrap_or_default(),
+                    },
+                })
+            })
+            .collect();
+        ChatMessage {
+            role: "assistant".to_string(),
+            content: MessageContent::default(),
+            tool_call_id: None,
+            tool_calls: Some(tool_calls),
+        }
+    }
+}
 
 /// A struct representing the decoding arguments for OpenAI API requests
 #[derive(Debug, PartialEq, Clone)]
@@ -85,22 +194,59 @@ fn prepare_prompt_batches(
     (0..num_prompt_batches)
         .map(|batch_id| {
             let start = batch_id * batch_size;
-            let end = (batch_id + 1) * batch_size;
+            let end = ((batch_id + 1) * batch_size).min(num_prompts);
             prompts[start..end].to_vec()
         })
         .collect()
 }
 
+/// Classifies a non-2xx response from an OpenAI-compatible completions API into
+/// an [`OpenAiError`], consuming the response to inspect its headers/body.
+async fn classify_error_response(response: Response) -> OpenAiError {
+    let status = response.status();
+
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return OpenAiError::RateLimited { retry_after };
+    }
+
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return OpenAiError::Auth;
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    if body.contains("Please reduce your prompt") || body.contains("maximum context length") {
+        return OpenAiError::ContextLengthExceeded;
+    }
+
+    OpenAiError::Other(format!("{}: {}", status, body))
+}
+
+/// Computes an exponential backoff delay with jitter for retry attempt `attempt`
+/// (1-indexed): `base_sleep_time * 2^(attempt - 1)`, plus up to 20% random jitter.
+fn backoff_delay(base_sleep_time: u64, attempt: u32) -> Duration {
+    let exp_secs = base_sleep_time.saturating_mul(1u64 << attempt.saturating_sub(1).min(16));
+    let jitter = rand::thread_rng().gen::<f64>() * 0.2 * exp_secs as f64;
+    Duration::from_secs_f64(exp_secs as f64 + jitter)
+}
+
 /// Asynchronously sends a request to the OpenAI API with the specified parameters and model.
 ///
-/// This function takes an HTTP Client, the API url, an api_key, a prompt_batch,
-/// decoding_args, and decoding_kwargs. It sends a request to the OpenAI API using
-/// the provided model and returns the completion choices as a Result<Vec<Value>>, /// Box<dyn Error>>.
+/// This function takes an HTTP Client, the API url, a ClientConfig, a model_name,
+/// a prompt_batch, decoding_args, and decoding_kwargs. It sends a request to the
+/// API using the provided model and returns the completion choices as a
+/// Result<Vec<Value>, Box<dyn Error>>.
 ///
 /// # Arguments
 /// * client - An HTTP client instance for sending the request.
-/// * url - The OpenAI API endpoint URL.
-/// * api_key - The API key for authentication with OpenAI.
+/// * url - The API endpoint URL.
+/// * config - Connection details (API key, organization) for the endpoint.
+/// * model_name - The model to request completions from.
 /// * prompt_batch - A slice of prompts to send to the API.
 /// * decoding_args - Decoding arguments for the API.
 /// * decoding_kwargs - Additional decoding arguments as a HashMap.
@@ -108,16 +254,17 @@ fn prepare_prompt_batches(
 /// # Returns
 /// * A Result containing a Vec<Value> of completion choices, or a Box<dyn Error>
 /// if an error occurs.
-async fn send_request(
+pub(crate) async fn send_request(
     client: &Client,
     url: &str,
-    api_key: &str,
+    config: &ClientConfig,
+    model_name: &str,
     prompt_batch: &[Value],
     decoding_args: &OpenAIDecodingArguments,
     decoding_kwargs: &HashMap<String, Value>,
 ) -> Result<Vec<Value>, Box<dyn Error>> {
     let mut request_data = HashMap::new();
-    request_data.insert("model".to_string(), "text-davinci-003".to_string());
+    request_data.insert("model".to_string(), model_name.to_string());
     request_data.insert("prompt".to_string(), serde_json::to_string(prompt_batch)?);
     request_data.insert(
         "max_tokens".to_string(),
@@ -145,47 +292,118 @@ async fn send_request(
         request_data.insert(key.clone(), value_str);
     }
 
-    let response = client
+    let mut request_builder = client
         .post(url)
         .json(&request_data)
-        .bearer_auth(api_key)
-        .send()
-        .await?;
+        .bearer_auth(&config.api_key);
+    if let Some(organization) = &config.organization {
+        request_builder = request_builder.header("OpenAI-Organization", organization);
+    }
+    let response = request_builder.send().await?;
 
     if response.status() != StatusCode::OK {
-        return Err(format!("OpenAIError: {}", response.status()).into());
+        return Err(Box::new(classify_error_response(response).await));
     }
     let completion_batch: Value = response.json().await?;
-    let choices = completion_batch["choices"].as_array().unwrap();
+    let choices = completion_batch["choices"].as_array().ok_or_else(|| {
+        Box::new(OpenAiError::Other(format!(
+            "response body missing a \"choices\" array: {}",
+            completion_batch
+        )))
+    })?;
     Ok(choices.iter().cloned().collect())
 }
 
-/// Sends a request to the OpenAI API to generate completions for the given prompt(s).
+/// Drives one prompt batch through `backend`, retrying with backoff (or a
+/// `max_tokens` reduction on a context-length error) until it succeeds or
+/// exhausts `max_retries`. Both retry kinds share the same `attempt` counter and
+/// cap, so a context-length error that isn't actually fixed by shrinking
+/// `max_tokens` still gives up instead of looping forever. `batch_id` is only
+/// used to label log output.
+async fn complete_batch_with_retry(
+    backend: &dyn CompletionBackend,
+    prompt_batch: &[Value],
+    mut batch_decoding_args: OpenAIDecodingArguments,
+    base_sleep_time: u64,
+    max_retries: u32,
+    batch_id: usize,
+) -> Result<Vec<Value>, Box<dyn Error>> {
+    let mut attempt = 0u32;
+
+    loop {
+        match backend.complete(prompt_batch, &batch_decoding_args).await {
+            Ok(choices) => return Ok(choices),
+            Err(err) => {
+                eprintln!("OpenAIError (batch {}): {}", batch_id, err);
+
+                match err.downcast_ref::<OpenAiError>() {
+                    Some(OpenAiError::ContextLengthExceeded) => {
+                        attempt += 1;
+                        if attempt > max_retries {
+                            return Err(err);
+                        }
+                        batch_decoding_args.max_tokens =
+                            (batch_decoding_args.max_tokens as f64 * 0.8) as u32;
+                        eprintln!(
+                            "Reducing target length to {}, Retrying ({}/{})...",
+                            batch_decoding_args.max_tokens, attempt, max_retries
+                        );
+                    }
+                    Some(OpenAiError::RateLimited { retry_after }) => {
+                        attempt += 1;
+                        if attempt > max_retries {
+                            return Err(err);
+                        }
+                        let delay =
+                            retry_after.unwrap_or_else(|| backoff_delay(base_sleep_time, attempt));
+                        eprintln!("Hit request rate limit; retrying in {:?}...", delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => return Err(err),
+                }
+            }
+        }
+    }
+}
+
+/// Sends a request to a [`CompletionBackend`] to generate completions for the given prompt(s).
+///
+/// The batching, retry, and `return_text`/`n`-chunking logic here is generic over
+/// the backend: it works identically whether `backend` talks to the OpenAI API, an
+/// Azure/proxy gateway, or a local/self-hosted OpenAI-compatible server.
 ///
 /// # Arguments
+/// * `backend` - The [`CompletionBackend`] to dispatch prompt batches to.
 /// * `prompt` - A single `Value` or an array of `Value`s representing the input prompt(s).
 /// * `decoding_args` - An `OpenAIDecodingArguments` struct containing decoding options for the API request.
-/// * `model_name` - A string slice with the name of the OpenAI model to use (e.g. "text-davinci-003").
-/// * `sleep_time` - The number of seconds to sleep between retries when the rate limit is hit.
+/// * `base_sleep_time` - The base number of seconds used for exponential backoff when rate-limited and the server sends no `Retry-After` header.
+/// * `max_retries` - The maximum number of retries per batch before giving up, counting both rate-limit retries and context-length retries together.
 /// * `batch_size` - The number of prompts to send in each request batch.
+/// * `max_client_batch_size` - A hard cap on the number of prompts per request, regardless of `batch_size`.
 /// * `max_instances` - The maximum number of instances (prompts) to process.
+/// * `concurrency` - The maximum number of batches to have in flight at once.
 /// * `return_text` - If `true`, only the generated text will be returned in the response; if `false`, the entire response object will be returned.
-/// * `decoding_kwargs` - A `HashMap<String, Value>` containing additional keyword arguments for decoding.
 ///
 /// # Returns
 /// A `Result<Vec<Value>, Box<dyn Error>>` containing either the generated completion(s) as a vector of `Value`s or an error.
 ///
 /// # Errors
-/// This function will return an error if the API request fails, or if there is a problem with the input arguments.
+/// Returns an [`crate::error::OpenAiError`] if any batch's backend request fails
+/// with an auth error, a non-retryable error, or a rate limit or context-length
+/// error that outlives `max_retries`. One throttled batch backs off independently and does not block
+/// the others.
+#[allow(clippy::too_many_arguments)]
 pub async fn openai_completion(
+    backend: &dyn CompletionBackend,
     prompt: Value,
     decoding_args: OpenAIDecodingArguments,
-    model_name: &str,
-    sleep_time: u64,
+    base_sleep_time: u64,
+    max_retries: u32,
     batch_size: usize,
+    max_client_batch_size: usize,
     max_instances: usize,
+    concurrency: usize,
     return_text: bool,
-    decoding_kwargs: HashMap<String, Value>,
 ) -> Result<Vec<Value>, Box<dyn Error>> {
     let single_prompt = is_single_prompt(&prompt);
     let prompts = if single_prompt {
@@ -194,51 +412,35 @@ pub async fn openai_completion(
         prompt.as_array().unwrap().clone()
     };
 
-    let prompt_batches = prepare_prompt_batches(prompts, batch_size, max_instances);
-    let client = Client::new();
-    let url = format!(
-        "https://api.openai.com/v1/engines/{}/completions",
-        model_name
-    );
-    let api_key = "your_openai_api_key"; // Replace with your OpenAI API key.
-    let mut completions = Vec::new();
+    let effective_batch_size = batch_size.min(max_client_batch_size).max(1);
+    let prompt_batches = prepare_prompt_batches(prompts, effective_batch_size, max_instances);
 
-    for (batch_id, prompt_batch) in prompt_batches.into_iter().enumerate() {
-        let mut batch_decoding_args = decoding_args.clone();
-        let mut success = false;
-
-        while !success {
-            match send_request(
-                &client,
-                &url,
-                api_key,
-                &prompt_batch,
-                &batch_decoding_args,
-                &decoding_kwargs,
-            )
-            .await
-            {
-                Ok(choices) => {
-                    completions.extend(choices);
-                    success = true;
+    let mut batch_results: Vec<BatchResult> =
+        stream::iter(prompt_batches.into_iter().enumerate())
+            .map(|(batch_id, prompt_batch)| {
+                let batch_decoding_args = decoding_args.clone();
+                async move {
+                    let result = complete_batch_with_retry(
+                        backend,
+                        &prompt_batch,
+                        batch_decoding_args,
+                        base_sleep_time,
+                        max_retries,
+                        batch_id,
+                    )
+                    .await;
+                    (batch_id, result)
                 }
-                Err(err) => {
-                    eprintln!("OpenAIError: {}", err);
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
 
-                    if err.to_string().contains("Please reduce your prompt") {
-                        batch_decoding_args.max_tokens =
-                            (batch_decoding_args.max_tokens as f64 * 0.8) as u32;
-                        eprintln!(
-                            "Reducing target length to {}, Retrying...",
-                            batch_decoding_args.max_tokens
-                        );
-                    } else {
-                        eprintln!("Hit request rate limit; retrying...");
-                        thread::sleep(Duration::from_secs(sleep_time));
-                    }
-                }
-            }
-        }
+    batch_results.sort_by_key(|(batch_id, _)| *batch_id);
+
+    let mut completions = Vec::new();
+    for (_, result) in batch_results {
+        completions.extend(result?);
     }
 
     if return_text {
@@ -264,6 +466,340 @@ pub async fn openai_completion(
     Ok(completions)
 }
 
+/// Asynchronously sends a Chat Completions request to the OpenAI API.
+///
+/// Unlike [`send_request`], which targets the legacy `v1/engines/{model}/completions`
+/// endpoint with a flat `prompt` string, this targets `v1/chat/completions` and
+/// serializes a `messages` array. Fields that only make sense for the legacy
+/// completions endpoint (`echo`, `suffix`, `logprobs`) are not applicable here and
+/// are omitted from the request body.
+///
+/// # Arguments
+/// * client - An HTTP client instance for sending the request.
+/// * url - The Chat Completions endpoint URL.
+/// * config - Connection details (API key, organization) for the endpoint.
+/// * model_name - The chat model to use (e.g. "gpt-3.5-turbo", "gpt-4").
+/// * messages - The conversation so far, as a sequence of `ChatMessage`s.
+/// * tools - The tools the model may call, if any.
+/// * tool_choice - How the model should pick among `tools`, if specified.
+/// * decoding_args - Decoding arguments for the API.
+/// * decoding_kwargs - Additional decoding arguments as a HashMap.
+///
+/// # Returns
+/// * A Result containing a Vec<Value> of `choices[i].message` objects (so callers
+/// can inspect `tool_calls` as well as `content`), or a Box<dyn Error> if an error occurs.
+#[allow(clippy::too_many_arguments)]
+async fn send_chat_request(
+    client: &Client,
+    url: &str,
+    config: &ClientConfig,
+    model_name: &str,
+    messages: &[ChatMessage],
+    tools: &[ToolSpec],
+    tool_choice: Option<&ToolChoice>,
+    decoding_args: &OpenAIDecodingArguments,
+    decoding_kwargs: &HashMap<String, Value>,
+) -> Result<Vec<Value>, Box<dyn Error>> {
+    let mut request_data = serde_json::json!({
+        "model": model_name,
+        "messages": messages,
+        "max_tokens": decoding_args.max_tokens,
+        "temperature": decoding_args.temperature,
+        "top_p": decoding_args.top_p,
+        "n": decoding_args.n,
+        "stream": decoding_args.stream,
+        "presence_penalty": decoding_args.presence_penalty,
+        "frequency_penalty": decoding_args.frequency_penalty,
+    });
+
+    if let Some(stop) = &decoding_args.stop {
+        request_data["stop"] = serde_json::to_value(stop)?;
+    }
+    if !tools.is_empty() {
+        request_data["tools"] = Value::Array(tools.iter().map(ToolSpec::to_request_value).collect());
+    }
+    if let Some(tool_choice) = tool_choice {
+        request_data["tool_choice"] = tool_choice.to_request_value();
+    }
+
+    let request_object = request_data.as_object_mut().unwrap();
+    for (key, value) in decoding_kwargs {
+        request_object.insert(key.clone(), value.clone());
+    }
+
+    let mut request_builder = client
+        .post(url)
+        .json(&request_data)
+        .bearer_auth(&config.api_key);
+    if let Some(organization) = &config.organization {
+        request_builder = request_builder.header("OpenAI-Organization", organization);
+    }
+    let response = request_builder.send().await?;
+
+    if response.status() != StatusCode::OK {
+        return Err(Box::new(classify_error_response(response).await));
+    }
+    let completion_batch: Value = response.json().await?;
+    let choices = completion_batch["choices"].as_array().ok_or_else(|| {
+        Box::new(OpenAiError::Other(format!(
+            "response body missing a \"choices\" array: {}",
+            completion_batch
+        )))
+    })?;
+    Ok(choices
+        .iter()
+        .map(|choice| choice["message"].clone())
+        .collect())
+}
+
+/// Sends a request to the OpenAI Chat Completions API to generate a reply for the
+/// given conversation.
+///
+/// This mirrors [`openai_completion`], but targets chat models (gpt-3.5/gpt-4 class)
+/// which no longer expose the legacy completions interface: it takes a `Vec` of
+/// role/content messages instead of a flat `prompt` string.
+///
+/// # Arguments
+/// * `config` - Connection details (base URL, API key, organization) for the endpoint.
+/// * `messages` - The conversation so far, as a sequence of `ChatMessage`s.
+/// * `decoding_args` - An `OpenAIDecodingArguments` struct containing decoding options for the API request.
+///   If left at its default `max_tokens` and `model_name` is vision-capable, `max_tokens` is raised to
+///   [`crate::vision::VISION_DEFAULT_MAX_TOKENS`].
+/// * `model_name` - A string slice with the name of the chat model to use (e.g. "gpt-3.5-turbo").
+/// * `tools` - The tools the model may call. Pass an empty `Vec` to disable function calling.
+/// * `tool_choice` - How the model should pick among `tools`, if specified.
+/// * `base_sleep_time` - The base number of seconds used for exponential backoff when rate-limited and the server sends no `Retry-After` header.
+/// * `max_retries` - The maximum number of times to retry a rate-limited request before giving up.
+/// * `return_text` - If `true`, only the generated reply text will be returned; if `false`, the entire `message` object (including any `tool_calls`) will be returned.
+/// * `decoding_kwargs` - A `HashMap<String, Value>` containing additional keyword arguments for decoding.
+///
+/// # Returns
+/// A `Result<Vec<Value>, Box<dyn Error>>` containing either the generated reply/replies
+/// (one per `n`) as a vector of `Value`s or an error.
+///
+/// # Errors
+/// Returns an [`crate::error::OpenAiError`] if the request fails with an auth
+/// error, a non-retryable error, or a rate limit that outlives `max_retries`.
+///
+/// # Multi-step tool-calling loop
+/// When a reply's `message.tool_calls` is non-empty, the caller is expected to run
+/// each requested tool, append [`ChatMessage::assistant_tool_calls`] followed by one
+/// [`ChatMessage::tool`] per result to `messages`, and call this function again —
+/// repeating until a reply comes back with no tool calls.
+#[allow(clippy::too_many_arguments)]
+pub async fn openai_chat_completion(
+    config: &ClientConfig,
+    messages: Vec<ChatMessage>,
+    mut decoding_args: OpenAIDecodingArguments,
+    model_name: &str,
+    tools: Vec<ToolSpec>,
+    tool_choice: Option<ToolChoice>,
+    base_sleep_time: u64,
+    max_retries: u32,
+    return_text: bool,
+    decoding_kwargs: HashMap<String, Value>,
+) -> Result<Vec<Value>, Box<dyn Error>> {
+    if decoding_args.max_tokens == OpenAIDecodingArguments::default().max_tokens
+        && is_vision_model(model_name)
+    {
+        decoding_args.max_tokens = VISION_DEFAULT_MAX_TOKENS;
+    }
+
+    let client = Client::new();
+    let url = format!("{}/v1/chat/completions", config.base_url.trim_end_matches('/'));
+    let mut completions;
+    let mut attempt = 0u32;
+
+    loop {
+        match send_chat_request(
+            &client,
+            &url,
+            config,
+            model_name,
+            &messages,
+            &tools,
+            tool_choice.as_ref(),
+            &decoding_args,
+            &decoding_kwargs,
+        )
+        .await
+        {
+            Ok(choices) => {
+                completions = choices;
+                break;
+            }
+            Err(err) => {
+                eprintln!("OpenAIError: {}", err);
+
+                match err.downcast_ref::<OpenAiError>() {
+                    Some(OpenAiError::RateLimited { retry_after }) => {
+                        attempt += 1;
+                        if attempt > max_retries {
+                            return Err(err);
+                        }
+                        let delay = retry_after
+                            .unwrap_or_else(|| backoff_delay(base_sleep_time, attempt));
+                        eprintln!("Hit request rate limit; retrying in {:?}...", delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => return Err(err),
+                }
+            }
+        }
+    }
+
+    if return_text {
+        completions = completions
+            .into_iter()
+            .map(|message| message["content"].clone())
+            .collect();
+    }
+
+    Ok(completions)
+}
+
+/// Parses one Server-Sent-Events frame (everything up to a `\n\n` separator) from
+/// the OpenAI streaming API into the text delta it carries, if any.
+///
+/// Returns `Ok(None)` for frames that carry no text delta (e.g. the initial
+/// role-only delta) and for the `[DONE]` sentinel, `Ok(Some(_))` for a text
+/// delta, and `Err` if the frame's payload isn't valid JSON.
+fn parse_sse_event(event: &str) -> Result<Option<String>, String> {
+    for line in event.lines() {
+        let Some(data) = line.strip_prefix("data: ") else {
+            continue;
+        };
+        if data == "[DONE]" {
+            return Ok(None);
+        }
+        let parsed: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if let Some(delta) = parsed["choices"][0]["delta"]["content"].as_str() {
+            return Ok(Some(delta.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Streams a Chat Completions reply from the OpenAI API token-by-token.
+///
+/// This sends the request with `"stream": true` and reads the response body as
+/// a byte stream of `text/event-stream` frames (`data: {...}\n\n`, terminated by a
+/// `data: [DONE]` frame) instead of waiting for `response.json()` to resolve the
+/// whole completion at once. Each yielded item is one incremental content delta,
+/// so callers can render tokens as they arrive.
+///
+/// # Arguments
+/// * `config` - Connection details (base URL, API key, organization) for the endpoint.
+/// * `messages` - The conversation so far, as a sequence of `ChatMessage`s.
+/// * `decoding_args` - Decoding arguments for the API. `stream` is forced to `true`, and `max_tokens`
+///   is raised to [`crate::vision::VISION_DEFAULT_MAX_TOKENS`] if left at its default and `model_name`
+///   is vision-capable.
+/// * `model_name` - The chat model to use (e.g. "gpt-3.5-turbo", "gpt-4").
+/// * `decoding_kwargs` - Additional decoding arguments as a HashMap.
+///
+/// # Returns
+/// A `Stream` yielding each text delta as it arrives, or an error if a frame's
+/// payload can't be parsed or the underlying connection fails.
+pub async fn openai_chat_completion_stream(
+    config: &ClientConfig,
+    messages: Vec<ChatMessage>,
+    mut decoding_args: OpenAIDecodingArguments,
+    model_name: &str,
+    decoding_kwargs: HashMap<String, Value>,
+) -> Result<impl Stream<Item = Result<String, Box<dyn Error>>>, Box<dyn Error>> {
+    decoding_args.stream = true;
+    if decoding_args.max_tokens == OpenAIDecodingArguments::default().max_tokens
+        && is_vision_model(model_name)
+    {
+        decoding_args.max_tokens = VISION_DEFAULT_MAX_TOKENS;
+    }
+
+    let client = Client::new();
+    let url = format!("{}/v1/chat/completions", config.base_url.trim_end_matches('/'));
+
+    let mut request_data = serde_json::json!({
+        "model": model_name,
+        "messages": messages,
+        "max_tokens": decoding_args.max_tokens,
+        "temperature": decoding_args.temperature,
+        "top_p": decoding_args.top_p,
+        "n": decoding_args.n,
+        "stream": true,
+        "presence_penalty": decoding_args.presence_penalty,
+        "frequency_penalty": decoding_args.frequency_penalty,
+    });
+    if let Some(stop) = &decoding_args.stop {
+        request_data["stop"] = serde_json::to_value(stop)?;
+    }
+    let request_object = request_data.as_object_mut().unwrap();
+    for (key, value) in decoding_kwargs {
+        request_object.insert(key, value);
+    }
+
+    let mut request_builder = client
+        .post(&url)
+        .json(&request_data)
+        .bearer_auth(&config.api_key);
+    if let Some(organization) = &config.organization {
+        request_builder = request_builder.header("OpenAI-Organization", organization);
+    }
+    let response = request_builder.send().await?;
+
+    if response.status() != StatusCode::OK {
+        return Err(format!("OpenAIError: {}", response.status()).into());
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel::<Result<String, String>>();
+
+    tokio::spawn(async move {
+        let mut byte_stream = response.bytes_stream();
+        let mut pending_bytes: Vec<u8> = Vec::new();
+        let mut buffer = String::new();
+
+        while let Some(chunk) = byte_stream.next().await {
+            let bytes = match chunk {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    let _ = tx.send(Err(err.to_string()));
+                    return;
+                }
+            };
+            pending_bytes.extend_from_slice(&bytes);
+
+            // A chunk boundary can split a multi-byte UTF-8 character in half, so only
+            // decode the valid prefix and keep the incomplete tail for the next read.
+            let valid_up_to = match std::str::from_utf8(&pending_bytes) {
+                Ok(_) => pending_bytes.len(),
+                Err(err) => err.valid_up_to(),
+            };
+            let valid_bytes: Vec<u8> = pending_bytes.drain(..valid_up_to).collect();
+            buffer.push_str(&String::from_utf8_lossy(&valid_bytes));
+
+            while let Some(pos) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..pos + 2).collect();
+                match parse_sse_event(event.trim_end()) {
+                    Ok(Some(delta)) => {
+                        if tx.send(Ok(delta)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        let _ = tx.send(Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|item| (item.map_err(|e| e.into()), rx))
+    }))
+}
+
 /// Create a BufWriter for the file.
 ///
 /// If the input is a file path, it opens the file in the specified mode.
@@ -332,3 +868,75 @@ pub fn jload<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(
     let obj = serde_json::from_reader(reader)?;
     Ok(obj)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_prompt_batches_splits_into_even_chunks() {
+        let prompts: Vec<Value> = (0..6u64).map(Value::from).collect();
+        let batches = prepare_prompt_batches(prompts, 2, usize::MAX);
+        assert_eq!(batches.len(), 3);
+        for batch in &batches {
+            assert_eq!(batch.len(), 2);
+        }
+    }
+
+    #[test]
+    fn prepare_prompt_batches_handles_non_multiple_count() {
+        let prompts: Vec<Value> = (0..5u64).map(Value::from).collect();
+        let batches = prepare_prompt_batches(prompts, 2, usize::MAX);
+        let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn prepare_prompt_batches_respects_max_instances() {
+        let prompts: Vec<Value> = (0..10u64).map(Value::from).collect();
+        let batches = prepare_prompt_batches(prompts, 3, 4);
+        let total: usize = batches.iter().map(Vec::len).sum();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn parse_sse_event_extracts_text_delta() {
+        let event = "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n";
+        assert_eq!(parse_sse_event(event).unwrap(), Some("hi".to_string()));
+    }
+
+    #[test]
+    fn parse_sse_event_done_sentinel_returns_none() {
+        let event = "data: [DONE]\n\n";
+        assert_eq!(parse_sse_event(event).unwrap(), None);
+    }
+
+    #[test]
+    fn parse_sse_event_role_only_delta_returns_none() {
+        let event = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n";
+        assert_eq!(parse_sse_event(event).unwrap(), None);
+    }
+
+    #[test]
+    fn parse_sse_event_invalid_json_is_an_error() {
+        let event = "data: not json\n\n";
+        assert!(parse_sse_event(event).is_err());
+    }
+
+    #[test]
+    fn backoff_delay_grows_exponentially_with_attempt() {
+        // Jitter adds up to 20%, so compare against the unjittered lower bound.
+        let base = 2;
+        let first = backoff_delay(base, 1).as_secs_f64();
+        let second = backoff_delay(base, 2).as_secs_f64();
+        assert!(first >= 2.0 && first < 2.0 * 1.2);
+        assert!(second >= 4.0 && second < 4.0 * 1.2);
+    }
+
+    #[test]
+    fn backoff_delay_saturates_instead_of_overflowing() {
+        // attempt is capped internally so a huge attempt count doesn't overflow the shift.
+        let delay = backoff_delay(1, u32::MAX);
+        assert!(delay.as_secs_f64().is_finite());
+    }
+}
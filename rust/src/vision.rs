@@ -0,0 +1,78 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// The `max_tokens` default applied when a vision-capable model is requested and
+/// the caller left [`crate::utils::OpenAIDecodingArguments`] at its own default,
+/// since image inputs tend to warrant longer replies than the plain-text default.
+pub const VISION_DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// A chat message's `content`, either plain text or (for vision-capable models) an
+/// ordered sequence of text/image parts.
+///
+/// Serializes to exactly what the Chat Completions API expects in either case: a
+/// bare string, or an array of `{"type": ..., ...}` objects.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+/// One part of a multimodal message's content.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+impl ContentPart {
+    /// Builds a text part.
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// Builds an image part from `source`.
+    ///
+    /// `source` is passed through as-is if it's a remote `http(s)://` URL.
+    /// Otherwise it's treated as a local file path: its bytes are read, its MIME
+    /// type is guessed from the extension, and it's inlined as a
+    /// `data:<mime>;base64,<...>` URL, since the Chat Completions API has no way
+    /// to fetch a file off the caller's disk itself.
+    pub fn image(source: &str) -> Result<Self, Box<dyn Error>> {
+        let url = if source.starts_with("http://") || source.starts_with("https://") {
+            source.to_string()
+        } else {
+            let bytes = std::fs::read(source)?;
+            let mime = mime_guess::from_path(source).first_or_octet_stream();
+            format!("data:{};base64,{}", mime, STANDARD.encode(bytes))
+        };
+        Ok(ContentPart::ImageUrl {
+            image_url: ImageUrl { url },
+        })
+    }
+}
+
+/// Whether `model_name` is a vision-capable chat model, used to raise the
+/// `max_tokens` default for image-bearing requests.
+pub fn is_vision_model(model_name: &str) -> bool {
+    model_name.contains("vision") || model_name.contains("gpt-4o") || model_name.contains("gpt-4-turbo")
+}